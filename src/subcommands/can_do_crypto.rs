@@ -0,0 +1,119 @@
+// Copyright 2023 Contributors to the Parsec project.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Discovers whether a provider can perform a given cryptographic operation.
+//!
+//! Uses the `CanDoCrypto` operation to ask the provider whether a key of the
+//! given type, size and policy is supported before attempting the operation.
+
+use crate::error::{Result, ToolErrorKind};
+use parsec_client::core::interface::operations::can_do_crypto::CheckType;
+use parsec_client::core::interface::operations::psa_algorithm::{
+    Algorithm, AsymmetricSignature, Hash, SignHash,
+};
+use parsec_client::core::interface::operations::psa_key_attributes::{
+    Attributes, EccFamily, Lifetime, Policy, Type, UsageFlags,
+};
+use parsec_client::BasicClient;
+use structopt::StructOpt;
+
+/// Queries the provider's capability-discovery API.
+#[derive(Debug, StructOpt)]
+pub struct CanDoCrypto {
+    /// Key type: one of `rsa-key-pair`, `rsa-public-key`, `ecc-key-pair`,
+    /// `ecc-public-key`.
+    #[structopt(long)]
+    key_type: String,
+
+    /// Key size in bits.
+    #[structopt(long)]
+    bits: usize,
+
+    /// Permitted algorithm: one of `ecdsa-sha256`, `ecdsa-sha384`,
+    /// `rsa-pkcs1v15-sign-sha256`, `rsa-pss-sha256`.
+    #[structopt(long)]
+    algorithm: String,
+}
+
+impl CanDoCrypto {
+    /// Queries the provider's can-do-crypto API and prints a support table.
+    pub fn run(&self, basic_client: BasicClient) -> Result<()> {
+        let attributes = self.attributes()?;
+
+        println!("{:<10} {}", "Check", "Supported");
+        for check in [
+            CheckType::Use,
+            CheckType::Generate,
+            CheckType::Import,
+            CheckType::Derive,
+        ] {
+            let supported = basic_client.can_do_crypto(check, attributes).is_ok();
+            println!("{:<10} {}", format!("{:?}", check), supported);
+        }
+
+        Ok(())
+    }
+
+    /// Builds the key attributes from the command-line arguments.
+    fn attributes(&self) -> Result<Attributes> {
+        let key_type = parse_key_type(&self.key_type)?;
+        let permitted_algorithms = parse_algorithm(&self.algorithm)?;
+
+        Ok(Attributes {
+            lifetime: Lifetime::Volatile,
+            key_type,
+            bits: self.bits,
+            policy: Policy {
+                usage_flags: {
+                    let mut flags = UsageFlags::default();
+                    let _ = flags
+                        .set_sign_hash()
+                        .set_verify_hash()
+                        .set_sign_message()
+                        .set_verify_message();
+                    flags
+                },
+                permitted_algorithms,
+            },
+        })
+    }
+}
+
+fn parse_key_type(value: &str) -> Result<Type> {
+    Ok(match value {
+        "rsa-key-pair" => Type::RsaKeyPair,
+        "rsa-public-key" => Type::RsaPublicKey,
+        "ecc-key-pair" => Type::EccKeyPair {
+            curve_family: EccFamily::SecpR1,
+        },
+        "ecc-public-key" => Type::EccPublicKey {
+            curve_family: EccFamily::SecpR1,
+        },
+        other => {
+            err!("Key type ({}) is not supported", other);
+            return Err(ToolErrorKind::NotSupported.into());
+        }
+    })
+}
+
+fn parse_algorithm(value: &str) -> Result<Algorithm> {
+    let alg = match value {
+        "ecdsa-sha256" => AsymmetricSignature::Ecdsa {
+            hash_alg: SignHash::Specific(Hash::Sha256),
+        },
+        "ecdsa-sha384" => AsymmetricSignature::Ecdsa {
+            hash_alg: SignHash::Specific(Hash::Sha384),
+        },
+        "rsa-pkcs1v15-sign-sha256" => AsymmetricSignature::RsaPkcs1v15Sign {
+            hash_alg: SignHash::Specific(Hash::Sha256),
+        },
+        "rsa-pss-sha256" => AsymmetricSignature::RsaPss {
+            hash_alg: SignHash::Specific(Hash::Sha256),
+        },
+        other => {
+            err!("Algorithm ({}) is not supported", other);
+            return Err(ToolErrorKind::NotSupported.into());
+        }
+    };
+    Ok(Algorithm::AsymmetricSignature(alg))
+}
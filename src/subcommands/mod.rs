@@ -0,0 +1,34 @@
+// Copyright 2021 Contributors to the Parsec project.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Subcommands exposed by the tool.
+
+pub mod can_do_crypto;
+pub mod sign;
+pub mod verify;
+
+use crate::error::Result;
+use parsec_client::BasicClient;
+use structopt::StructOpt;
+
+/// The subcommand dispatched from the command line.
+#[derive(Debug, StructOpt)]
+pub enum Subcommand {
+    /// Sign data with a Parsec key.
+    Sign(sign::Sign),
+    /// Verify a signature.
+    Verify(verify::Verify),
+    /// Discover whether a provider can perform a cryptographic operation.
+    CanDoCrypto(can_do_crypto::CanDoCrypto),
+}
+
+impl Subcommand {
+    /// Runs the selected subcommand.
+    pub fn run(&self, basic_client: BasicClient) -> Result<()> {
+        match self {
+            Subcommand::Sign(cmd) => cmd.run(basic_client),
+            Subcommand::Verify(cmd) => cmd.run(basic_client),
+            Subcommand::CanDoCrypto(cmd) => cmd.run(basic_client),
+        }
+    }
+}
@@ -6,11 +6,18 @@
 //! Will use the algorithm set to the key's policy during creation.
 
 use crate::error::{Result, ToolErrorKind};
-use parsec_client::core::interface::operations::psa_algorithm::{Algorithm, Hash, SignHash};
+use parsec_client::core::interface::operations::can_do_crypto::CheckType;
+use parsec_client::core::interface::operations::psa_algorithm::{
+    Algorithm, AsymmetricSignature, Hash, SignHash,
+};
 use parsec_client::BasicClient;
 use picky_asn1::wrapper::IntegerAsn1;
 use serde::{Deserialize, Serialize};
 use sha2::digest::{Digest, DynDigest};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
 use structopt::StructOpt;
 
 /// Signs data.
@@ -19,41 +26,127 @@ pub struct Sign {
     #[structopt(short = "k", long = "key-name")]
     key_name: String,
 
-    /// String of UTF-8 text
-    input_data: String,
+    /// String of UTF-8 text. If omitted, the data is read from
+    /// `--input-file` or, failing that, from standard input.
+    input_data: Option<String>,
+
+    /// Read the data to sign from this file as raw bytes, allowing
+    /// non-UTF-8 input.
+    #[structopt(long, parse(from_os_str))]
+    input_file: Option<PathBuf>,
+
+    /// Treat the input as an already-computed digest and sign it
+    /// directly, bypassing local hashing.
+    #[structopt(long)]
+    hash_input: bool,
+
+    /// Encoding of the printed signature.
+    #[structopt(long, default_value = "base64", possible_values = &["base64", "hex", "raw"])]
+    output: OutputEncoding,
 
     /// Encode the signature in ASN.1 format (for ECC signatures
     /// only).
     #[structopt(long)]
     encode_asn1: bool,
+
+    /// Sign the raw message instead of a locally-computed hash, letting
+    /// the provider perform the hashing (uses `psa_sign_message`).
+    #[structopt(long)]
+    message: bool,
+
+    /// Emit a JWS compact serialization (JWT) over the input data instead
+    /// of a bare base64 signature.
+    #[structopt(long)]
+    jws: bool,
 }
 
 #[derive(Serialize, Deserialize)]
-struct EccSignature {
-    r: IntegerAsn1,
-    s: IntegerAsn1,
+pub(crate) struct EccSignature {
+    pub r: IntegerAsn1,
+    pub s: IntegerAsn1,
+}
+
+/// Encoding used to print the produced signature.
+#[derive(Debug, Clone, Copy)]
+enum OutputEncoding {
+    Base64,
+    Hex,
+    Raw,
+}
+
+impl OutputEncoding {
+    /// Writes the signature to standard output in the chosen encoding.
+    fn write(&self, signature: &[u8]) -> Result<()> {
+        match self {
+            OutputEncoding::Base64 => println!("{}", base64::encode(signature)),
+            OutputEncoding::Hex => println!("{}", hex::encode(signature)),
+            OutputEncoding::Raw => std::io::stdout().write_all(signature)?,
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for OutputEncoding {
+    type Err = ToolErrorKind;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "base64" => Ok(OutputEncoding::Base64),
+            "hex" => Ok(OutputEncoding::Hex),
+            "raw" => Ok(OutputEncoding::Raw),
+            _ => Err(ToolErrorKind::NotSupported),
+        }
+    }
 }
 
 impl Sign {
     /// Signs data.
     pub fn run(&self, basic_client: BasicClient) -> Result<()> {
-        let alg = basic_client
-            .key_attributes(&self.key_name)?
-            .policy
-            .permitted_algorithms;
+        self.check_conflicts()?;
+
+        let attributes = basic_client.key_attributes(&self.key_name)?;
+        let alg = attributes.policy.permitted_algorithms;
+
+        let input = self.input()?;
+
+        if self.jws {
+            return self.run_jws(basic_client, alg, &input);
+        }
 
         let signature = match alg {
             Algorithm::AsymmetricSignature(alg) => {
-                info!("Hashing data...");
-                let hash = match alg.hash() {
-                    Some(SignHash::Specific(hash)) => hash_data(self.input_data.as_bytes(), hash)?,
-                    _ => {
-                        err!("Asymmetric signing algorithm ({:?}) is not supported", alg);
-                        return Err(ToolErrorKind::NotSupported.into());
+                info!("Signing data...");
+                let result = if self.message {
+                    basic_client.psa_sign_message(self.key_name.clone(), &input, alg)
+                } else if self.hash_input {
+                    // The input is already a digest; sign it as-is.
+                    basic_client.psa_sign_hash(self.key_name.clone(), &input, alg)
+                } else {
+                    info!("Hashing data...");
+                    let hash = match alg.hash() {
+                        Some(SignHash::Specific(hash)) => hash_data(&input, hash)?,
+                        _ => {
+                            err!("Asymmetric signing algorithm ({:?}) is not supported", alg);
+                            return Err(ToolErrorKind::NotSupported.into());
+                        }
+                    };
+                    basic_client.psa_sign_hash(self.key_name.clone(), &hash, alg)
+                };
+
+                let mut sig = match result {
+                    Ok(sig) => sig,
+                    Err(e) => {
+                        // Signing failed. Ask capability discovery about the
+                        // key's attributes and, if that query returns an error,
+                        // report it before surfacing the original signing
+                        // error. Providers without `CanDoCrypto` also return an
+                        // error from the query, so they get the report too.
+                        if basic_client.can_do_crypto(CheckType::Use, attributes).is_err() {
+                            err!("Provider reports it cannot sign with {:?}", alg);
+                        }
+                        return Err(e.into());
                     }
                 };
-                info!("Signing data...");
-                let mut sig = basic_client.psa_sign_hash(self.key_name.clone(), &hash, alg)?;
                 if alg.is_ecc_alg() && self.encode_asn1 {
                     let mut asn1_signature = vec![0; 1000];
                     let size = picky_asn1_der::to_bytes(
@@ -79,15 +172,126 @@ impl Sign {
             }
         };
 
-        let signature = base64::encode(&signature);
+        self.output.write(&signature)?;
 
-        println!("{}", signature);
+        Ok(())
+    }
 
+    /// Rejects mutually-exclusive flag combinations up-front. `--message` and
+    /// `--hash-input` select incompatible signing inputs, and `--jws` drives
+    /// its own signing and output path, so it cannot be combined with
+    /// `--message`, `--hash-input` or `--encode-asn1`. `--output` always has
+    /// a value (default `base64`) and is not checked: `--jws` ignores it.
+    fn check_conflicts(&self) -> Result<()> {
+        if self.message && self.hash_input {
+            err!("`--message` and `--hash-input` are mutually exclusive");
+            return Err(ToolErrorKind::IncorrectData.into());
+        }
+        if self.jws && (self.message || self.hash_input || self.encode_asn1) {
+            err!("`--jws` cannot be combined with `--message`, `--hash-input` or `--encode-asn1`");
+            return Err(ToolErrorKind::IncorrectData.into());
+        }
         Ok(())
     }
+
+    /// Reads the data to sign from the argument, `--input-file`, or stdin.
+    fn input(&self) -> Result<Vec<u8>> {
+        if let Some(data) = &self.input_data {
+            Ok(data.as_bytes().to_vec())
+        } else if let Some(path) = &self.input_file {
+            Ok(fs::read(path)?)
+        } else {
+            let mut buffer = Vec::new();
+            std::io::stdin().read_to_end(&mut buffer)?;
+            Ok(buffer)
+        }
+    }
+
+    /// Produces a JWS compact serialization over the input data.
+    fn run_jws(&self, basic_client: BasicClient, alg: Algorithm, input: &[u8]) -> Result<()> {
+        let alg = match alg {
+            Algorithm::AsymmetricSignature(alg) => alg,
+            other => {
+                err!(
+                    "Key's algorithm is {:?} which can not be used for signing.",
+                    other
+                );
+                return Err(ToolErrorKind::WrongKeyAlgorithm.into());
+            }
+        };
+
+        let hash = match alg.hash() {
+            Some(SignHash::Specific(hash)) => hash,
+            _ => {
+                err!("Asymmetric signing algorithm ({:?}) is not supported", alg);
+                return Err(ToolErrorKind::NotSupported.into());
+            }
+        };
+
+        let header = format!("{{\"alg\":\"{}\",\"typ\":\"JWT\"}}", jws_alg(alg)?);
+        let signing_input = format!(
+            "{}.{}",
+            base64::encode_config(header, base64::URL_SAFE_NO_PAD),
+            base64::encode_config(input, base64::URL_SAFE_NO_PAD),
+        );
+
+        info!("Hashing signing input...");
+        let digest = hash_data(signing_input.as_bytes(), hash)?;
+
+        info!("Signing data...");
+        // The client returns the raw `r||s` concatenation for ECDSA, which is
+        // exactly the form JWS mandates, so the signature is used as-is.
+        let signature = basic_client.psa_sign_hash(self.key_name.clone(), &digest, alg)?;
+
+        println!(
+            "{}.{}",
+            signing_input,
+            base64::encode_config(signature, base64::URL_SAFE_NO_PAD)
+        );
+
+        Ok(())
+    }
+}
+
+/// Maps a PSA asymmetric signature algorithm to its JWS `alg` value.
+fn jws_alg(alg: AsymmetricSignature) -> Result<&'static str> {
+    use AsymmetricSignature::{Ecdsa, RsaPkcs1v15Sign, RsaPss};
+    let value = match alg {
+        Ecdsa {
+            hash_alg: SignHash::Specific(hash),
+        } => match hash {
+            Hash::Sha256 => "ES256",
+            Hash::Sha384 => "ES384",
+            Hash::Sha512 => "ES512",
+            _ => return unsupported_jws_alg(alg),
+        },
+        RsaPkcs1v15Sign {
+            hash_alg: SignHash::Specific(hash),
+        } => match hash {
+            Hash::Sha256 => "RS256",
+            Hash::Sha384 => "RS384",
+            Hash::Sha512 => "RS512",
+            _ => return unsupported_jws_alg(alg),
+        },
+        RsaPss {
+            hash_alg: SignHash::Specific(hash),
+        } => match hash {
+            Hash::Sha256 => "PS256",
+            Hash::Sha384 => "PS384",
+            Hash::Sha512 => "PS512",
+            _ => return unsupported_jws_alg(alg),
+        },
+        _ => return unsupported_jws_alg(alg),
+    };
+    Ok(value)
+}
+
+fn unsupported_jws_alg(alg: AsymmetricSignature) -> Result<&'static str> {
+    err!("Algorithm ({:?}) has no JWS mapping", alg);
+    Err(ToolErrorKind::NotSupported.into())
 }
 
-fn hash_data(data: &[u8], alg: Hash) -> Result<Vec<u8>> {
+pub(crate) fn hash_data(data: &[u8], alg: Hash) -> Result<Vec<u8>> {
     let mut hasher: Box<dyn DynDigest> = match alg {
         Hash::Sha224 => Box::from(sha2::Sha224::new()),
         Hash::Sha256 => Box::from(sha2::Sha256::new()),
@@ -0,0 +1,308 @@
+// Copyright 2023 Contributors to the Parsec project.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Verifies a signature.
+//!
+//! With `--key-name`, uses the algorithm set to the key's policy during
+//! creation. With `--public-key-file`, the algorithm comes from the key file
+//! and, for RSA, from `--rsa-scheme` and `--rsa-hash`.
+
+use crate::error::{Result, ToolErrorKind};
+use crate::subcommands::sign::{hash_data, EccSignature};
+use parsec_client::core::interface::operations::psa_algorithm::{Algorithm, SignHash};
+use parsec_client::BasicClient;
+use std::fs;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Verifies a signature.
+#[derive(Debug, StructOpt)]
+pub struct Verify {
+    /// Name of the Parsec key to verify against. Mutually exclusive with
+    /// `--public-key-file`.
+    #[structopt(
+        short = "k",
+        long = "key-name",
+        required_unless = "public-key-file",
+        conflicts_with = "public-key-file"
+    )]
+    key_name: Option<String>,
+
+    /// DER/PEM `SubjectPublicKeyInfo` file to verify against locally,
+    /// without contacting Parsec.
+    #[structopt(long, parse(from_os_str))]
+    public_key_file: Option<PathBuf>,
+
+    /// String of UTF-8 text that was signed
+    input_data: String,
+
+    /// Base64-encoded signature to verify
+    signature: String,
+
+    /// The signature is encoded in ASN.1 format (for ECC signatures
+    /// only).
+    #[structopt(long)]
+    decode_asn1: bool,
+
+    /// Verify over the raw message instead of a locally-computed hash,
+    /// letting the provider perform the hashing (uses
+    /// `psa_verify_message`).
+    #[structopt(long)]
+    message: bool,
+
+    /// Hash used by an RSA signature. Required when verifying RSA against an
+    /// external `--public-key-file`, as the `rsaEncryption` OID encodes
+    /// neither the hash nor the padding scheme. One of `sha256`, `sha384`,
+    /// `sha512`.
+    #[structopt(long)]
+    rsa_hash: Option<String>,
+
+    /// Padding scheme used by an RSA signature. Required alongside
+    /// `--rsa-hash` when verifying RSA against an external `--public-key-file`.
+    /// One of `pkcs1v15`, `pss`.
+    #[structopt(long)]
+    rsa_scheme: Option<String>,
+}
+
+impl Verify {
+    /// Verifies a signature.
+    pub fn run(&self, basic_client: BasicClient) -> Result<()> {
+        if let Some(path) = &self.public_key_file {
+            return self.run_external(path);
+        }
+
+        let key_name = self.key_name.clone().ok_or(ToolErrorKind::IncorrectData)?;
+        let attributes = basic_client.key_attributes(&key_name)?;
+        let alg = attributes.policy.permitted_algorithms;
+
+        let signature = base64::decode(&self.signature)?;
+
+        match alg {
+            Algorithm::AsymmetricSignature(alg) => {
+                let signature = if alg.is_ecc_alg() && self.decode_asn1 {
+                    let decoded: EccSignature = picky_asn1_der::from_bytes(&signature)
+                        .map_err(|_| ToolErrorKind::WrongKeyAlgorithm)?;
+                    // The raw signature is the fixed-width `r||s`
+                    // concatenation, each component left-zero-padded to
+                    // the curve's coordinate size.
+                    let coordinate_size = (attributes.bits + 7) / 8;
+                    let mut raw = Vec::with_capacity(2 * coordinate_size);
+                    raw.extend(pad_left(decoded.r.as_unsigned_bytes_be(), coordinate_size));
+                    raw.extend(pad_left(decoded.s.as_unsigned_bytes_be(), coordinate_size));
+                    raw
+                } else {
+                    signature
+                };
+
+                info!("Verifying signature...");
+                if self.message {
+                    basic_client.psa_verify_message(
+                        key_name.clone(),
+                        self.input_data.as_bytes(),
+                        alg,
+                        &signature,
+                    )?;
+                } else {
+                    info!("Hashing data...");
+                    let hash = match alg.hash() {
+                        Some(SignHash::Specific(hash)) => {
+                            hash_data(self.input_data.as_bytes(), hash)?
+                        }
+                        _ => {
+                            err!("Asymmetric signing algorithm ({:?}) is not supported", alg);
+                            return Err(ToolErrorKind::NotSupported.into());
+                        }
+                    };
+                    basic_client.psa_verify_hash(key_name.clone(), &hash, alg, &signature)?;
+                }
+            }
+            other => {
+                err!(
+                    "Key's algorithm is {:?} which can not be used for verification.",
+                    other
+                );
+                return Err(ToolErrorKind::WrongKeyAlgorithm.into());
+            }
+        };
+
+        println!("Signature verified.");
+
+        Ok(())
+    }
+}
+
+impl Verify {
+    /// Verifies the signature against an external `SubjectPublicKeyInfo`,
+    /// without contacting Parsec.
+    fn run_external(&self, path: &std::path::Path) -> Result<()> {
+        let signature = base64::decode(&self.signature)?;
+        let der = read_spki_der(path)?;
+
+        // The algorithm OID in the SPKI blob selects the scheme and, for
+        // ECC, the curve determines the hash to use. An `rsaEncryption` OID
+        // encodes neither the hash nor PSS-vs-PKCS1v15, so those must be
+        // supplied explicitly for RSA.
+        match spki_algorithm(&der)? {
+            SpkiAlgorithm::Rsa => {
+                self.verify_rsa(&der, &signature)?;
+            }
+            SpkiAlgorithm::EccP256 => {
+                use p256::ecdsa::signature::Verifier;
+                use p256::ecdsa::{Signature, VerifyingKey};
+                use p256::pkcs8::DecodePublicKey;
+                let key = VerifyingKey::from_public_key_der(&der)
+                    .map_err(|_| ToolErrorKind::WrongKeyAlgorithm)?;
+                info!("Verifying ECDSA P-256 signature...");
+                let signature = ecc_signature::<32>(&signature)?;
+                let signature = Signature::try_from(signature.as_slice())
+                    .map_err(|_| ToolErrorKind::WrongKeyAlgorithm)?;
+                key.verify(self.input_data.as_bytes(), &signature)
+                    .map_err(|_| ToolErrorKind::WrongKeyAlgorithm)?;
+            }
+            SpkiAlgorithm::EccP384 => {
+                use p384::ecdsa::signature::Verifier;
+                use p384::ecdsa::{Signature, VerifyingKey};
+                use p384::pkcs8::DecodePublicKey;
+                let key = VerifyingKey::from_public_key_der(&der)
+                    .map_err(|_| ToolErrorKind::WrongKeyAlgorithm)?;
+                info!("Verifying ECDSA P-384 signature...");
+                let signature = ecc_signature::<48>(&signature)?;
+                let signature = Signature::try_from(signature.as_slice())
+                    .map_err(|_| ToolErrorKind::WrongKeyAlgorithm)?;
+                key.verify(self.input_data.as_bytes(), &signature)
+                    .map_err(|_| ToolErrorKind::WrongKeyAlgorithm)?;
+            }
+        }
+
+        println!("Signature verified.");
+
+        Ok(())
+    }
+
+    /// Verifies an RSA signature against an external public key, recovering
+    /// the padding scheme and hash from `--rsa-scheme`/`--rsa-hash`. Both are
+    /// mandatory, as the SPKI `rsaEncryption` OID does not carry them.
+    fn verify_rsa(&self, der: &[u8], signature: &[u8]) -> Result<()> {
+        use rsa::pkcs8::DecodePublicKey;
+        use rsa::sha2::{Sha256, Sha384, Sha512};
+        use rsa::signature::Verifier;
+        use rsa::RsaPublicKey;
+
+        let hash = self.rsa_hash.as_deref().ok_or_else(|| {
+            err!("`--rsa-hash` is required to verify an RSA signature against a public-key file");
+            ToolErrorKind::IncorrectData
+        })?;
+        let scheme = self.rsa_scheme.as_deref().ok_or_else(|| {
+            err!("`--rsa-scheme` is required to verify an RSA signature against a public-key file");
+            ToolErrorKind::IncorrectData
+        })?;
+
+        let key = RsaPublicKey::from_public_key_der(der)
+            .map_err(|_| ToolErrorKind::WrongKeyAlgorithm)?;
+        let message = self.input_data.as_bytes();
+
+        macro_rules! verify_with {
+            ($module:ident, $digest:ty) => {{
+                use rsa::$module::{Signature, VerifyingKey};
+                let key = VerifyingKey::<$digest>::new(key);
+                let signature =
+                    Signature::try_from(signature).map_err(|_| ToolErrorKind::WrongKeyAlgorithm)?;
+                key.verify(message, &signature)
+                    .map_err(|_| ToolErrorKind::WrongKeyAlgorithm)?;
+            }};
+        }
+
+        info!("Verifying RSA {} signature with {}...", scheme, hash);
+        match (scheme, hash) {
+            ("pkcs1v15", "sha256") => verify_with!(pkcs1v15, Sha256),
+            ("pkcs1v15", "sha384") => verify_with!(pkcs1v15, Sha384),
+            ("pkcs1v15", "sha512") => verify_with!(pkcs1v15, Sha512),
+            ("pss", "sha256") => verify_with!(pss, Sha256),
+            ("pss", "sha384") => verify_with!(pss, Sha384),
+            ("pss", "sha512") => verify_with!(pss, Sha512),
+            _ => {
+                err!("Unsupported RSA scheme/hash combination: {}/{}", scheme, hash);
+                return Err(ToolErrorKind::NotSupported.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The public-key algorithm recovered from an SPKI blob.
+enum SpkiAlgorithm {
+    Rsa,
+    EccP256,
+    EccP384,
+}
+
+/// Reads a `SubjectPublicKeyInfo` file, accepting either PEM or raw DER.
+fn read_spki_der(path: &std::path::Path) -> Result<Vec<u8>> {
+    let contents = fs::read(path)?;
+    if contents.starts_with(b"-----BEGIN") {
+        let pem = pem::parse(&contents).map_err(|_| ToolErrorKind::WrongKeyAlgorithm)?;
+        Ok(pem.contents().to_vec())
+    } else {
+        Ok(contents)
+    }
+}
+
+/// Reads the algorithm identifier from an SPKI blob to select the scheme
+/// and, for ECC, the curve.
+fn spki_algorithm(der: &[u8]) -> Result<SpkiAlgorithm> {
+    use spki::der::Decode;
+    use spki::SubjectPublicKeyInfoRef;
+
+    const RSA: spki::ObjectIdentifier =
+        spki::ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+    const EC: spki::ObjectIdentifier = spki::ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+    const P256: spki::ObjectIdentifier =
+        spki::ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+    const P384: spki::ObjectIdentifier = spki::ObjectIdentifier::new_unwrap("1.3.132.0.34");
+
+    let spki = SubjectPublicKeyInfoRef::from_der(der)
+        .map_err(|_| ToolErrorKind::WrongKeyAlgorithm)?;
+
+    if spki.algorithm.oid == RSA {
+        Ok(SpkiAlgorithm::Rsa)
+    } else if spki.algorithm.oid == EC {
+        let curve = spki
+            .algorithm
+            .parameters_oid()
+            .map_err(|_| ToolErrorKind::WrongKeyAlgorithm)?;
+        if curve == P256 {
+            Ok(SpkiAlgorithm::EccP256)
+        } else if curve == P384 {
+            Ok(SpkiAlgorithm::EccP384)
+        } else {
+            err!("Unsupported ECC curve: {}", curve);
+            Err(ToolErrorKind::NotSupported.into())
+        }
+    } else {
+        err!("Unsupported public key algorithm: {}", spki.algorithm.oid);
+        Err(ToolErrorKind::NotSupported.into())
+    }
+}
+
+/// Normalises an ECDSA signature to the fixed-width `r||s` form, accepting
+/// both the ASN.1 `EccSignature` encoding produced by `Sign` and the raw
+/// `r||s` form. `N` is the curve's coordinate size in bytes.
+fn ecc_signature<const N: usize>(signature: &[u8]) -> Result<Vec<u8>> {
+    if signature.len() == 2 * N {
+        return Ok(signature.to_vec());
+    }
+    let decoded: EccSignature =
+        picky_asn1_der::from_bytes(signature).map_err(|_| ToolErrorKind::WrongKeyAlgorithm)?;
+    let mut raw = Vec::with_capacity(2 * N);
+    raw.extend(pad_left(decoded.r.as_unsigned_bytes_be(), N));
+    raw.extend(pad_left(decoded.s.as_unsigned_bytes_be(), N));
+    Ok(raw)
+}
+
+/// Left-zero-pads `bytes` to `size`, preserving the least significant bytes.
+fn pad_left(bytes: &[u8], size: usize) -> Vec<u8> {
+    let mut padded = vec![0; size.saturating_sub(bytes.len())];
+    padded.extend_from_slice(bytes);
+    padded
+}